@@ -1,9 +1,10 @@
 use clap::{Parser, Subcommand};
 use eyre::Result;
+use indexmap::map::Entry;
+use indexmap::{IndexMap, IndexSet};
 use rayon::prelude::*;
 use serde::Serialize;
 use serde_json::Value;
-use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
 use std::fmt;
@@ -92,15 +93,15 @@ impl KMBool {
 #[derive(Debug, Serialize)]
 struct KMNumber {
     files: KMFiles,
-    int: HashSet<i64>,
-    float: HashSet<u64>,
+    int: IndexSet<i64>,
+    float: IndexSet<u64>,
 }
 impl KMNumber {
     fn new() -> Self {
         Self {
             files: HashSet::new(),
-            int: HashSet::new(),
-            float: HashSet::new(),
+            int: IndexSet::new(),
+            float: IndexSet::new(),
         }
     }
     fn merge(&mut self, other: Self) {
@@ -118,18 +119,42 @@ impl KMNumber {
         Box::new(self.files.iter())
     }
 }
+/// Splits a string value into lowercased, whitespace/punctuation separated terms
+/// for the `--index-values` inverted index.
+fn tokenize(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+}
+
 #[derive(Debug, Serialize)]
 struct KMString {
     files: KMFiles,
+    /// Inverted index from tokenized term to the files whose value contained it,
+    /// populated only when `--index-values` is set.
+    index: HashMap<String, KMFiles>,
 }
 impl KMString {
     fn new() -> Self {
         Self {
             files: HashSet::new(),
+            index: HashMap::new(),
         }
     }
+    fn add(&mut self, file: Arc<PathBuf>, value: &str, index_values: bool) {
+        if index_values {
+            for term in tokenize(value) {
+                self.index.entry(term).or_default().insert(file.clone());
+            }
+        }
+        self.files.insert(file);
+    }
     fn merge(&mut self, other: Self) {
         self.files.extend(other.files);
+        for (term, files) in other.index {
+            self.index.entry(term).or_default().extend(files);
+        }
     }
     fn is_empty(&self) -> bool {
         self.files.is_empty()
@@ -143,7 +168,9 @@ impl KMString {
 }
 #[derive(Debug, Serialize)]
 struct KMArray {
-    items: Option<Box<KMTypes>>,
+    /// Analytics for the merged elements of all arrays seen at this key, addressable
+    /// by `query` via the `[]`/`[n]` operators just like any other nested key.
+    items: Option<Box<KeyMap>>,
     min_len: usize,
     max_len: usize,
 }
@@ -155,8 +182,8 @@ impl KMArray {
             items: None,
         }
     }
-    fn _get_items(&mut self) -> &mut Box<KMTypes> {
-        self.items.get_or_insert(Box::new(KMTypes::new()))
+    fn _get_items(&mut self) -> &mut Box<KeyMap> {
+        self.items.get_or_insert(Box::new(KeyMap::new()))
     }
     fn merge(&mut self, other: Self) {
         self.min_len = std::cmp::min(self.min_len, other.min_len);
@@ -165,22 +192,22 @@ impl KMArray {
             self._get_items().merge(*items);
         }
     }
-    fn add(&mut self, file: Arc<PathBuf>, json_value: &serde_json::Value) {
-        self._get_items().add(file, json_value);
+    fn add(&mut self, file: Arc<PathBuf>, json_value: &serde_json::Value, index_values: bool) {
+        self._get_items().add_value(&file, json_value, index_values);
     }
     fn is_empty(&self) -> bool {
-        self.items.is_none() || self.items.as_ref().unwrap().is_empty()
+        self.items.is_none() || self.items.as_ref().unwrap().types.is_empty()
     }
     fn count(&self) -> usize {
         if let Some(items) = &self.items {
-            items.count()
+            items.types.count()
         } else {
             0
         }
     }
     fn files(&self) -> Box<dyn Iterator<Item = &Arc<PathBuf>> + '_> {
         if let Some(items) = &self.items {
-            items.files()
+            items.types.files()
         } else {
             Box::new(std::iter::empty())
         }
@@ -191,7 +218,7 @@ impl Display for KMArray {
         let count = self.count();
         write!(f, "[")?;
         if let Some(items) = &self.items {
-            write!(f, "{items} ")?;
+            write!(f, "{} ", items.types)?;
         }
         write!(f, "]={count} ")
     }
@@ -248,7 +275,7 @@ impl KMTypes {
             object: KMObject::new(),
         }
     }
-    fn add(&mut self, file: Arc<PathBuf>, json_value: &serde_json::Value) {
+    fn add(&mut self, file: Arc<PathBuf>, json_value: &serde_json::Value, index_values: bool) {
         match json_value {
             Value::Null => {
                 self.null.files.insert(file);
@@ -268,8 +295,8 @@ impl KMTypes {
                     self.number.float.insert(n.as_f64().unwrap().to_bits());
                 }
             }
-            Value::String(_) => {
-                self.string.files.insert(file);
+            Value::String(s) => {
+                self.string.add(file, s, index_values);
             }
             Value::Array(array) => {
                 let len = array.len();
@@ -281,7 +308,7 @@ impl KMTypes {
                 }
                 for item in array {
                     let clone_path = file.clone();
-                    self.array.add(clone_path, item);
+                    self.array.add(clone_path, item, index_values);
                 }
             }
             Value::Object(_map) => {
@@ -379,11 +406,14 @@ impl Display for KMTypes {
 struct KeyMap {
     count: u64,
     types: KMTypes,
-    keys: HashMap<KeyString, KeyMap>,
+    /// Preserves first-seen order so the order of keys in listings and query output
+    /// is deterministic instead of depending on hash iteration order. The file sets
+    /// and the value index are still hash sets/maps, so their order is not defined.
+    keys: IndexMap<KeyString, KeyMap>,
 }
 impl KeyMap {
     fn new() -> Self {
-        let keys = HashMap::new();
+        let keys = IndexMap::new();
         let types = KMTypes::new();
         Self {
             keys,
@@ -392,19 +422,26 @@ impl KeyMap {
         }
     }
 
-    fn add(&mut self, file: &Arc<PathBuf>, name: &str, value: &serde_json::Value) {
+    fn add(&mut self, file: &Arc<PathBuf>, name: &str, value: &serde_json::Value, index_values: bool) {
         let sub_tree = self
             .keys
             .entry(KeyString::new(name))
             .or_insert_with(KeyMap::new);
         sub_tree.count += 1;
+        sub_tree.add_value(file, value, index_values);
+    }
+
+    /// Record a JSON value that isn't reached through a named member, such as an
+    /// element of an array. Unlike `add`, this doesn't bump `count` since there is
+    /// no enclosing key whose occurrences it would track.
+    fn add_value(&mut self, file: &Arc<PathBuf>, value: &serde_json::Value, index_values: bool) {
         if let Value::Object(v_map) = value {
             for (k, v) in v_map {
-                sub_tree.add(file, k, v);
+                self.add(file, k, v, index_values);
             }
         }
         let file = file.clone();
-        sub_tree.types.add(file, value);
+        self.types.add(file, value, index_values);
     }
 
     fn merge(&mut self, other: Self) {
@@ -433,47 +470,225 @@ fn read_json_file<P: AsRef<Path>>(path: P) -> Result<Value> {
     Ok(value)
 }
 
-fn print_sub_keys<'tree>(tree: &'tree KeyMap, type_count:u8, prefix: &mut Vec<&'tree KeyString>) {
-    let count = tree.count;
-    let types = &tree.types;
-    if !types.is_object() && types.type_count() >= type_count{
-        print!("{count} '");
-        for p in prefix.iter() {
-            print!(".{p}");
+/// Recursively replace `{"$ref": "#/a/b"}` objects with the value they point to,
+/// so the analytics see the referenced structure instead of a literal `$ref` key.
+///
+/// Only local JSON Pointers (RFC 6901, starting with `#/`) are resolved; external
+/// refs are left untouched. `chain` tracks the pointers currently being resolved
+/// so a cycle leaves the cycle-closing node unresolved instead of looping forever.
+fn resolve_refs(value: &Value, root: &Value, chain: &mut HashSet<String>) -> Value {
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::String(pointer)) = map.get("$ref").filter(|_| map.len() == 1) {
+                match pointer.strip_prefix('#').filter(|local| local.starts_with('/')) {
+                    Some(local) if chain.insert(pointer.clone()) => {
+                        let referent = root.pointer(local).unwrap_or(&Value::Null);
+                        let resolved = resolve_refs(referent, root, chain);
+                        chain.remove(pointer);
+                        return resolved;
+                    }
+                    _ => return value.clone(),
+                }
+            }
+            Value::Object(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), resolve_refs(v, root, chain)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|v| resolve_refs(v, root, chain)).collect())
         }
-        println!("' {types}");
+        other => other.clone(),
     }
-    for (k, v) in &tree.keys {
-        prefix.push(k);
-        print_sub_keys(v, type_count, prefix);
-        prefix.pop();
+}
+
+fn print_key_line(tree: &KeyMap, prefix: &[&KeyString]) {
+    print!("{} '", tree.count);
+    for p in prefix {
+        print!(".{p}");
     }
+    println!("' {}", tree.types);
 }
 
-fn print_keys(tree: &KeyMap, type_count:u8) {
+fn print_sub_keys<'tree>(
+    tree: &'tree KeyMap,
+    type_count: u8,
+    sort: bool,
+    prefix: &mut Vec<&'tree KeyString>,
+) {
+    let types = &tree.types;
+    if !types.is_object() && types.type_count() >= type_count {
+        print_key_line(tree, prefix);
+    }
+    if sort {
+        let mut entries: Vec<_> = tree.keys.iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.0.cmp(&b.0));
+        for (k, v) in entries {
+            prefix.push(k);
+            print_sub_keys(v, type_count, sort, prefix);
+            prefix.pop();
+        }
+    } else {
+        for (k, v) in &tree.keys {
+            prefix.push(k);
+            print_sub_keys(v, type_count, sort, prefix);
+            prefix.pop();
+        }
+    }
+}
+
+fn print_keys(tree: &KeyMap, type_count: u8, sort: bool) {
     let mut prefix: Vec<&KeyString> = vec![];
-    print_sub_keys(&tree, type_count, &mut prefix)
+    print_sub_keys(tree, type_count, sort, &mut prefix)
+}
+
+fn type_kind_is_empty(types: &KMTypes, kind: query::TypeKind) -> bool {
+    match kind {
+        query::TypeKind::Null => types.null.is_empty(),
+        query::TypeKind::Bool => types.bool.is_empty(),
+        query::TypeKind::String => types.string.is_empty(),
+        query::TypeKind::Number => types.number.is_empty(),
+        query::TypeKind::Array => types.array.is_empty(),
+        query::TypeKind::Object => types.object.is_empty(),
+    }
+}
+
+/// A single printable path segment of a query match: either a real key, or the
+/// `[]` array-iteration step that descends into `KMArray.items`.
+#[derive(Debug, Clone, Copy)]
+enum PathSeg<'tree> {
+    Key(&'tree KeyString),
+    Iterate,
+}
+
+impl Display for PathSeg<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSeg::Key(k) => write!(f, ".{k}"),
+            PathSeg::Iterate => write!(f, "[]"),
+        }
+    }
+}
+
+/// A node reached while evaluating a query, paired with the chain of path
+/// segments that led to it so matches found via `*`/`**` can still be reported
+/// with their full path.
+type Match<'tree> = (&'tree KeyMap, Vec<PathSeg<'tree>>);
+
+/// Every child of `node` reachable by one `*`/`**` level: its named members plus,
+/// when present, the merged array-element analytics (addressed by `[]`) that
+/// chunk0-1 made queryable just like any other nested key.
+fn children<'tree>(node: &'tree KeyMap) -> impl Iterator<Item = (PathSeg<'tree>, &'tree KeyMap)> {
+    node.keys
+        .iter()
+        .map(|(k, sub)| (PathSeg::Key(k), sub))
+        .chain(
+            node.types
+                .array
+                .items
+                .as_deref()
+                .map(|items| (PathSeg::Iterate, items)),
+        )
+}
+
+fn eval_step<'tree>(worklist: Vec<Match<'tree>>, step: &query::Step) -> Vec<Match<'tree>> {
+    match step {
+        query::Step::Key(name) => {
+            let name = KeyString::new(name);
+            worklist
+                .into_iter()
+                .filter_map(|(node, prefix)| {
+                    node.keys.get_key_value(&name).map(|(k, sub)| {
+                        let mut prefix = prefix.clone();
+                        prefix.push(PathSeg::Key(k));
+                        (sub, prefix)
+                    })
+                })
+                .collect()
+        }
+        query::Step::Index(_) | query::Step::Iterate => worklist
+            .into_iter()
+            .filter_map(|(node, prefix)| {
+                node.types.array.items.as_deref().map(|items| {
+                    let mut prefix = prefix.clone();
+                    prefix.push(PathSeg::Iterate);
+                    (items, prefix)
+                })
+            })
+            .collect(),
+        query::Step::Wildcard => worklist
+            .into_iter()
+            .flat_map(|(node, prefix)| {
+                children(node)
+                    .map(|(seg, sub)| {
+                        let mut prefix = prefix.clone();
+                        prefix.push(seg);
+                        (sub, prefix)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect(),
+        query::Step::Recurse => {
+            // `**` is zero-or-more levels, so each starting node matches itself
+            // too, not just its descendants.
+            let mut matches: Vec<Match<'tree>> =
+                worklist.iter().map(|(node, prefix)| (*node, prefix.clone())).collect();
+            let mut visited: HashSet<*const KeyMap> = HashSet::new();
+            let mut stack: Vec<Match<'tree>> = worklist;
+            while let Some((node, prefix)) = stack.pop() {
+                for (seg, sub) in children(node) {
+                    if !visited.insert(std::ptr::from_ref(sub)) {
+                        continue;
+                    }
+                    let mut child_prefix = prefix.clone();
+                    child_prefix.push(seg);
+                    matches.push((sub, child_prefix.clone()));
+                    stack.push((sub, child_prefix));
+                }
+            }
+            matches
+        }
+        query::Step::Predicate(kind) => worklist
+            .into_iter()
+            .filter(|(node, _)| !type_kind_is_empty(&node.types, *kind))
+            .collect(),
+        query::Step::Pipe => worklist,
+    }
+}
+
+fn print_match_line(tree: &KeyMap, prefix: &[PathSeg]) {
+    print!("{} '", tree.count);
+    for p in prefix {
+        print!("{p}");
+    }
+    println!("' {}", tree.types);
 }
 
 fn print_query(tree: &KeyMap, q: &str) -> Result<()> {
-    let (_rest, keys) =
+    let (_rest, steps) =
         query::query(q).map_err(|e| eyre::eyre!("Failed to parse query:\n\t{}", e))?;
-    let mut tree = tree;
-    for k in keys {
-        match tree.keys.get(&KeyString::new(k)) {
-            None => {
-                return Err(eyre::eyre!("Could not resolve key {}", k));
-            }
-            Some(sub_tree) => {
-                tree = sub_tree;
-            }
+    let is_multi = steps
+        .iter()
+        .any(|s| matches!(s, query::Step::Wildcard | query::Step::Recurse));
+
+    let mut matches: Vec<Match> = vec![(tree, Vec::new())];
+    for step in &steps {
+        matches = eval_step(matches, step);
+        if matches.is_empty() {
+            return Err(eyre::eyre!("Could not resolve query '{q}'"));
         }
     }
-    //for f in tree.types.files() {
-    //        println!("{}", f.strip_prefix(&args.dir)?.to_string_lossy());
-    //}
-    let json_str = serde_json::to_string(tree)?;
-    println!("{json_str}");
+
+    if is_multi {
+        for (node, prefix) in &matches {
+            print_match_line(node, prefix);
+        }
+    } else {
+        let (node, _) = matches[0];
+        let json_str = serde_json::to_string(node)?;
+        println!("{json_str}");
+    }
     Ok(())
 }
 
@@ -481,43 +696,63 @@ fn print_query(tree: &KeyMap, q: &str) -> Result<()> {
 #[clap(author, version, about, long_about = None)]
 struct Args {
     dir: PathBuf,
+    /// build an inverted index of string values so `search` can look files up by
+    /// content; costs extra memory, so it's off by default
+    #[clap(long)]
+    index_values: bool,
     #[clap(subcommand)]
     cmd: Command,
 }
 
 #[derive(Subcommand, Debug)]
 enum Command {
-    /// Query the analytics of a specific member 
+    /// Query the analytics of a specific member
     Query {
-        /// the query is similar to a jq query ".a.b.c"
+        /// the query is similar to a jq query, e.g. ".a.b.c", ".items[].price",
+        /// ".a | .b[2]" or ".**.id[string]"
         query: String
     },
     /// List all member keys with types and how often this member is in the dataset
     Keys {
         /// filter all member which have at lest [TYPE_COUNT] types
         #[clap(long, default_value_t = 1)]
-        type_count: u8 
+        type_count: u8,
+        /// emit keys lexicographically instead of in first-seen order
+        #[clap(long)]
+        sort: bool,
+    },
+    /// Compare the schema of `dir` against another directory of JSON files
+    Diff {
+        /// the directory to diff `dir` against
+        other: PathBuf,
+    },
+    /// Find files whose value at `query` contains `term` (requires --index-values)
+    Search {
+        /// the query is similar to a jq query, e.g. ".status"
+        query: String,
+        /// the term to look up in the value index for the resolved member
+        term: String,
     },
 }
 
-fn main() -> Result<()> {
-    let args = Args::parse();
+fn build_tree(dir: &Path, index_values: bool) -> KeyMap {
     let ext = Some(OsStr::new("json"));
-    let files: Vec<_> = WalkDir::new(&args.dir)
+    let files: Vec<_> = WalkDir::new(dir)
         .into_iter()
         .filter_map(Result::ok)
         .filter(|e| e.file_type().is_file() && e.path().extension() == ext)
         .map(|entry| Arc::new(entry.into_path()))
         .collect();
-    let tree = files
+    files
         .par_iter()
         .filter_map(|file| match read_json_file(&**file) {
             Err(_) => None,
             Ok(json) => {
+                let json = resolve_refs(&json, &json, &mut HashSet::new());
                 let mut sub_map = KeyMap::new();
                 if let Value::Object(m) = json {
                     for (k, v) in m {
-                        sub_map.add(file, &k, &v);
+                        sub_map.add(file, &k, &v, index_values);
                     }
                 }
                 Some(sub_map)
@@ -526,13 +761,161 @@ fn main() -> Result<()> {
         .reduce(KeyMap::new, |mut a, b| {
             a.merge(b);
             a
-        });
+        })
+}
+
+#[derive(Default)]
+struct DiffReport {
+    added: Vec<String>,
+    removed: Vec<String>,
+    /// Paths present on both sides whose `KMTypes` variant set differs.
+    changed: Vec<(String, String, String)>,
+    /// Paths present on both sides with the same variant set but a significant
+    /// change in observed count.
+    count_drift: Vec<(String, u64, u64)>,
+}
+
+/// All `TypeKind` variants, in the order they're checked elsewhere in this file.
+const ALL_TYPE_KINDS: [query::TypeKind; 6] = [
+    query::TypeKind::Null,
+    query::TypeKind::Bool,
+    query::TypeKind::String,
+    query::TypeKind::Number,
+    query::TypeKind::Array,
+    query::TypeKind::Object,
+];
+
+/// The set of `KMTypes` variants actually present (non-empty) on `types`.
+fn type_kind_set(types: &KMTypes) -> IndexSet<query::TypeKind> {
+    ALL_TYPE_KINDS
+        .into_iter()
+        .filter(|kind| !type_kind_is_empty(types, *kind))
+        .collect()
+}
+
+/// Below this relative change, a count drift is considered noise rather than a
+/// significant schema change worth reporting.
+const SIGNIFICANT_COUNT_RATIO: f64 = 0.2;
+
+/// Whether `before` and `after` differ by more than `SIGNIFICANT_COUNT_RATIO`.
+fn is_significant_count_change(before: u64, after: u64) -> bool {
+    let diff = before.abs_diff(after) as f64;
+    let baseline = before.max(after) as f64;
+    baseline > 0.0 && diff / baseline > SIGNIFICANT_COUNT_RATIO
+}
+
+/// Walk `a` and `b` in lockstep, recording for every key path whether it only
+/// exists in `a` (`removed`), only in `b` (`added`), exists in both with a
+/// changed `KMTypes` variant set (`changed`), or exists in both with the same
+/// variant set but a significant change in count (`count_drift`). Only paths
+/// through `keys` are walked; members of array elements (`KMArray.items`) are not
+/// compared.
+fn diff_keys(a: &KeyMap, b: &KeyMap, prefix: &mut Vec<String>, report: &mut DiffReport) {
+    let mut names: IndexSet<&str> = IndexSet::new();
+    names.extend(a.keys.keys().map(|k| k.0.as_str()));
+    names.extend(b.keys.keys().map(|k| k.0.as_str()));
+    for name in names {
+        prefix.push(name.to_string());
+        let path = format!(".{}", prefix.join("."));
+        match (a.keys.get(&KeyString::new(name)), b.keys.get(&KeyString::new(name))) {
+            (Some(_), None) => report.removed.push(path),
+            (None, Some(_)) => report.added.push(path),
+            (Some(a_sub), Some(b_sub)) => {
+                let a_kinds = type_kind_set(&a_sub.types);
+                let b_kinds = type_kind_set(&b_sub.types);
+                if a_kinds != b_kinds {
+                    let before = a_kinds.iter().map(|k| k.to_string()).collect::<Vec<_>>().join("+");
+                    let after = b_kinds.iter().map(|k| k.to_string()).collect::<Vec<_>>().join("+");
+                    report.changed.push((path.clone(), before, after));
+                } else if is_significant_count_change(a_sub.count, b_sub.count) {
+                    report.count_drift.push((path.clone(), a_sub.count, b_sub.count));
+                }
+                diff_keys(a_sub, b_sub, prefix, report);
+            }
+            (None, None) => unreachable!("name came from one of the two key maps"),
+        }
+        prefix.pop();
+    }
+}
+
+fn print_diff(a: &KeyMap, b: &KeyMap) {
+    let mut report = DiffReport::default();
+    diff_keys(a, b, &mut Vec::new(), &mut report);
+
+    println!("Added:");
+    for path in &report.added {
+        println!("  {path}");
+    }
+    println!("Removed:");
+    for path in &report.removed {
+        println!("  {path}");
+    }
+    println!("Changed:");
+    for (path, before, after) in &report.changed {
+        println!("  {path}: {before}-> {after}");
+    }
+    println!("Count drift:");
+    for (path, before, after) in &report.count_drift {
+        println!("  {path}: {before}-> {after}");
+    }
+}
+
+/// Resolve `query` and, for each resolved member, look up `term` in its string
+/// value index, printing the matching paths ranked by how many files share it.
+fn print_search(tree: &KeyMap, dir: &Path, q: &str, term: &str, index_values: bool) -> Result<()> {
+    if !index_values {
+        return Err(eyre::eyre!(
+            "value index is empty; re-run with --index-values"
+        ));
+    }
+
+    let (_rest, steps) =
+        query::query(q).map_err(|e| eyre::eyre!("Failed to parse query:\n\t{}", e))?;
+
+    let mut matches: Vec<Match> = vec![(tree, Vec::new())];
+    for step in &steps {
+        matches = eval_step(matches, step);
+        if matches.is_empty() {
+            return Err(eyre::eyre!("Could not resolve query '{q}'"));
+        }
+    }
+
+    let term = term.to_lowercase();
+    let mut hits: Vec<_> = matches
+        .into_iter()
+        .filter_map(|(node, prefix)| node.types.string.index.get(&term).map(|files| (prefix, files)))
+        .collect();
+    hits.sort_by_key(|(_, files)| std::cmp::Reverse(files.len()));
+
+    for (prefix, files) in hits {
+        print!("{} '", files.len());
+        for p in &prefix {
+            print!("{p}");
+        }
+        println!("'");
+        for file in files {
+            println!("  {}", file.strip_prefix(dir).unwrap_or(file).display());
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let args = Args::parse();
+    let tree = build_tree(&args.dir, args.index_values);
     match &args.cmd {
         Command::Query { query } => {
-            print_query(&tree, &query)?;
+            print_query(&tree, query)?;
+        }
+        Command::Keys { type_count, sort } => {
+            print_keys(&tree, *type_count, *sort);
+        }
+        Command::Diff { other } => {
+            let other_tree = build_tree(other, args.index_values);
+            print_diff(&tree, &other_tree);
         }
-        Command::Keys{type_count} => {
-            print_keys(&tree, *type_count);
+        Command::Search { query, term } => {
+            print_search(&tree, &args.dir, query, term, args.index_values)?;
         }
     }
     Ok(())
@@ -1,44 +1,273 @@
 use nom::{
     branch::alt,
-    bytes::complete::is_not,
-    character::complete::char,
-    combinator::all_consuming,
-    multi::separated_list0,
-    sequence::{preceded, terminated},
+    bytes::complete::{is_not, tag},
+    character::complete::{char, digit1, multispace0},
+    combinator::{all_consuming, map, map_res, value},
+    multi::{many0, separated_list1},
+    sequence::{delimited, preceded},
     IResult,
 };
 
+/// The `KMTypes` variant named by a trailing type predicate such as `[string]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TypeKind {
+    Null,
+    Bool,
+    String,
+    Number,
+    Array,
+    Object,
+}
+
+impl std::fmt::Display for TypeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TypeKind::Null => "null",
+            TypeKind::Bool => "bool",
+            TypeKind::String => "string",
+            TypeKind::Number => "number",
+            TypeKind::Array => "array",
+            TypeKind::Object => "object",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A single step of a parsed query, evaluated against a `KeyMap`/`KMTypes` tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    /// Descend into a named member, e.g. the `a` in `.a`.
+    Key(String),
+    /// Descend into the merged array element analytics, e.g. the `2` in `[2]`.
+    ///
+    /// Since array analytics are merged across all elements of all arrays, this
+    /// currently resolves the same subtree as `Iterate`.
+    Index(usize),
+    /// The `[]` operator: descend into the merged array element analytics.
+    Iterate,
+    /// The `*` operator: match every member at this level, plus the merged array
+    /// element analytics (`[]`) when there are any.
+    Wildcard,
+    /// The `**` operator: match the current node itself and every descendant
+    /// (members and array element analytics), at any depth.
+    Recurse,
+    /// A trailing `[type]` predicate, e.g. `[string]`: keep only subtrees that
+    /// have a non-empty `KMTypes` variant of that kind.
+    Predicate(TypeKind),
+    /// The `|` operator: sequence evaluation on the subtree reached so far.
+    Pipe,
+}
+
 fn quoted_key(input: &str) -> IResult<&str, &str> {
-    preceded(char('\"'), terminated(is_not("\""), char('\"')))(input)
+    preceded(char('"'), nom::sequence::terminated(is_not("\""), char('"')))(input)
+}
+
+fn bare_key(input: &str) -> IResult<&str, &str> {
+    is_not("\"\n\t .[]|*")(input)
+}
+
+fn type_kind(input: &str) -> IResult<&str, TypeKind> {
+    alt((
+        value(TypeKind::Null, tag("null")),
+        value(TypeKind::Bool, tag("bool")),
+        value(TypeKind::String, tag("string")),
+        value(TypeKind::Number, tag("number")),
+        value(TypeKind::Array, tag("array")),
+        value(TypeKind::Object, tag("object")),
+    ))(input)
+}
+
+fn bracket(input: &str) -> IResult<&str, Step> {
+    delimited(
+        char('['),
+        alt((
+            map_res(digit1, |d: &str| d.parse::<usize>().map(Step::Index)),
+            map(type_kind, Step::Predicate),
+            value(Step::Iterate, nom::combinator::success(())),
+        )),
+        char(']'),
+    )(input)
+}
+
+fn wildcard(input: &str) -> IResult<&str, Step> {
+    alt((
+        value(Step::Recurse, tag("**")),
+        value(Step::Wildcard, char('*')),
+    ))(input)
+}
+
+fn component(input: &str) -> IResult<&str, Vec<Step>> {
+    let (input, mut steps) = alt((
+        map(wildcard, |step| vec![step]),
+        map(alt((quoted_key, bare_key)), |name: &str| {
+            vec![Step::Key(name.to_string())]
+        }),
+    ))(input)?;
+    let (input, brackets) = many0(bracket)(input)?;
+    steps.extend(brackets);
+    Ok((input, steps))
 }
 
-fn key(input: &str) -> IResult<&str, &str> {
-    is_not("\"\n\t .")(input)
+fn segment(input: &str) -> IResult<&str, Vec<Step>> {
+    map(
+        preceded(char('.'), separated_list1(char('.'), component)),
+        |components: Vec<Vec<Step>>| components.into_iter().flatten().collect(),
+    )(input)
 }
 
-pub fn query(input: &str) -> IResult<&str, Vec<&str>> {
-    all_consuming(preceded(
-        char('.'),
-        separated_list0(char('.'), alt((quoted_key, key))),
+pub fn query(input: &str) -> IResult<&str, Vec<Step>> {
+    all_consuming(map(
+        separated_list1(delimited(multispace0, char('|'), multispace0), segment),
+        |segments: Vec<Vec<Step>>| {
+            let mut steps = Vec::new();
+            for (i, segment) in segments.into_iter().enumerate() {
+                if i > 0 {
+                    steps.push(Step::Pipe);
+                }
+                steps.extend(segment);
+            }
+            steps
+        },
     ))(input)
 }
 
 #[test]
 fn parse_query() {
-    assert_eq!(query("a.b.v"), Ok(("", vec!["a", "b", "v"])));
+    assert_eq!(
+        query(".a.b.v"),
+        Ok((
+            "",
+            vec![
+                Step::Key("a".to_string()),
+                Step::Key("b".to_string()),
+                Step::Key("v".to_string()),
+            ]
+        ))
+    );
 }
 #[test]
 fn parse_quoted_query() {
-    assert_eq!(query("a.\"b\".v"), Ok(("", vec!["a", "b", "v"])));
+    assert_eq!(
+        query(".a.\"b\".v"),
+        Ok((
+            "",
+            vec![
+                Step::Key("a".to_string()),
+                Step::Key("b".to_string()),
+                Step::Key("v".to_string()),
+            ]
+        ))
+    );
 }
 #[test]
 fn parse_quoted_dot_query() {
-    assert_eq!(query("a.\"b.b\".v"), Ok(("", vec!["a", "b.b", "v"])));
+    assert_eq!(
+        query(".a.\"b.b\".v"),
+        Ok((
+            "",
+            vec![
+                Step::Key("a".to_string()),
+                Step::Key("b.b".to_string()),
+                Step::Key("v".to_string()),
+            ]
+        ))
+    );
 }
 #[test]
 fn parse_quoted_dot_query2() {
     assert_eq!(
-        query("a.\"b\n.\t  b\".v"),
-        Ok(("", vec!["a", "b\n.\t  b", "v"]))
+        query(".a.\"b\n.\t  b\".v"),
+        Ok((
+            "",
+            vec![
+                Step::Key("a".to_string()),
+                Step::Key("b\n.\t  b".to_string()),
+                Step::Key("v".to_string()),
+            ]
+        ))
+    );
+}
+#[test]
+fn parse_iterate_query() {
+    assert_eq!(
+        query(".items[].price"),
+        Ok((
+            "",
+            vec![
+                Step::Key("items".to_string()),
+                Step::Iterate,
+                Step::Key("price".to_string()),
+            ]
+        ))
+    );
+}
+#[test]
+fn parse_index_query() {
+    assert_eq!(
+        query(".items[2].price"),
+        Ok((
+            "",
+            vec![
+                Step::Key("items".to_string()),
+                Step::Index(2),
+                Step::Key("price".to_string()),
+            ]
+        ))
+    );
+}
+#[test]
+fn parse_wildcard_query() {
+    assert_eq!(
+        query(".a.*.v"),
+        Ok((
+            "",
+            vec![
+                Step::Key("a".to_string()),
+                Step::Wildcard,
+                Step::Key("v".to_string()),
+            ]
+        ))
+    );
+}
+#[test]
+fn parse_recurse_query() {
+    assert_eq!(
+        query(".**.id"),
+        Ok((
+            "",
+            vec![
+                Step::Recurse,
+                Step::Key("id".to_string()),
+            ]
+        ))
+    );
+}
+#[test]
+fn parse_type_predicate_query() {
+    assert_eq!(
+        query(".**.id[string]"),
+        Ok((
+            "",
+            vec![
+                Step::Recurse,
+                Step::Key("id".to_string()),
+                Step::Predicate(TypeKind::String),
+            ]
+        ))
+    );
+}
+#[test]
+fn parse_pipe_query() {
+    assert_eq!(
+        query(".items[] | .price"),
+        Ok((
+            "",
+            vec![
+                Step::Key("items".to_string()),
+                Step::Iterate,
+                Step::Pipe,
+                Step::Key("price".to_string()),
+            ]
+        ))
     );
 }